@@ -0,0 +1,62 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::models::gr2m::GR2MModel;
+use crate::models::gr4j::GR4JModel;
+
+/*
+One independent simulation unit within a batch: a model instance paired
+with its own forcing columns, e.g. a single catchment or grid cell.
+*/
+pub enum BatchModel {
+    GR4J(GR4JModel),
+    GR2M(GR2MModel),
+}
+
+impl BatchModel {
+    fn run(&mut self, precip: &[f64], pet: &[f64]) -> Vec<f64> {
+        match self {
+            BatchModel::GR4J(model) => model.run(precip, pet),
+            BatchModel::GR2M(model) => model.run(precip, pet),
+        }
+    }
+}
+
+/*
+Run many independent `GR4JModel`/`GR2MModel` instances concurrently,
+one per catchment or grid cell, each with its own parameter set and
+forcing columns, and return the simulated streamflow for each.
+
+`models[i]` is run against `forcings[i] = (precip, pet)`. With the
+`parallel` cargo feature enabled the batch is distributed across a
+rayon thread pool; without it, single-model users pay nothing and the
+batch runs sequentially.
+*/
+pub fn run_batch(
+    mut models: Vec<BatchModel>,
+    forcings: &[(Vec<f64>, Vec<f64>)],
+) -> Vec<Vec<f64>> {
+    assert_eq!(
+        models.len(),
+        forcings.len(),
+        "one forcing pair is required per model"
+    );
+
+    #[cfg(feature = "parallel")]
+    {
+        models
+            .par_iter_mut()
+            .zip(forcings)
+            .map(|(model, (precip, pet))| model.run(precip, pet))
+            .collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        models
+            .iter_mut()
+            .zip(forcings)
+            .map(|(model, (precip, pet))| model.run(precip, pet))
+            .collect()
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::models::gr2m::{GR2MModel, GR2MParams};
+use crate::models::gr4j::{GR4JModel, GR4JParams};
+
+/*
+Sampling distribution for a single calibration parameter.
+*/
+#[derive(Clone, Copy, Debug)]
+pub enum ParamRange {
+    Uniform { min: f64, max: f64 },
+    Triangular { min: f64, mode: f64, max: f64 },
+}
+
+impl ParamRange {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match *self {
+            ParamRange::Uniform { min, max } => rng.gen_range(min..max),
+            ParamRange::Triangular { min, mode, max } => {
+                let u: f64 = rng.gen_range(0.0..1.0);
+                let fc = (mode - min) / (max - min);
+
+                if u < fc {
+                    min + (u * (max - min) * (mode - min)).sqrt()
+                } else {
+                    max - ((1. - u) * (max - min) * (max - mode)).sqrt()
+                }
+            }
+        }
+    }
+}
+
+/*
+Which conceptual model the ensemble should drive.
+*/
+pub enum ModelChoice {
+    GR4J,
+    GR2M,
+}
+
+/*
+A likelihood function used to weight sampled parameter sets against
+an observed streamflow series under the GLUE framework.
+*/
+pub trait Likelihood {
+    fn score(&self, qsim: &[f64], observed: &[f64]) -> f64;
+}
+
+/*
+Nash-Sutcliffe efficiency, the most common GLUE likelihood measure.
+*/
+pub struct NashSutcliffe;
+
+impl Likelihood for NashSutcliffe {
+    fn score(&self, qsim: &[f64], observed: &[f64]) -> f64 {
+        let mean_observed = observed.iter().sum::<f64>() / observed.len() as f64;
+
+        let numerator: f64 = qsim
+            .iter()
+            .zip(observed)
+            .map(|(s, o)| (o - s).powf(2.))
+            .sum();
+
+        let denominator: f64 = observed.iter().map(|o| (o - mean_observed).powf(2.)).sum();
+
+        1. - numerator / denominator
+    }
+}
+
+/*
+A single sampled, behavioural parameter set together with its
+likelihood score. Carries only the parameters the model it was sampled
+for actually uses, mirroring `ModelChoice`.
+*/
+pub enum BehaviouralParams {
+    GR4J {
+        x1: f64,
+        x2: f64,
+        x3: f64,
+        x4: f64,
+        likelihood: f64,
+    },
+    GR2M {
+        x1: f64,
+        x2: f64,
+        likelihood: f64,
+    },
+}
+
+impl BehaviouralParams {
+    fn likelihood(&self) -> f64 {
+        match *self {
+            BehaviouralParams::GR4J { likelihood, .. } => likelihood,
+            BehaviouralParams::GR2M { likelihood, .. } => likelihood,
+        }
+    }
+}
+
+/*
+Per-timestep 5/50/95% prediction interval plus the retained
+behavioural parameter sets, following the Generalized Likelihood
+Uncertainty Estimation (GLUE) approach of Beven and Binley (1992).
+*/
+pub struct EnsembleResult {
+    pub q05: Vec<f64>,
+    pub q50: Vec<f64>,
+    pub q95: Vec<f64>,
+    pub behavioural: Vec<BehaviouralParams>,
+}
+
+fn weighted_quantile(values: &mut [(f64, f64)], quantile: f64) -> f64 {
+    values.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    if values.is_empty() {
+        return 0.;
+    }
+
+    let total_weight: f64 = values.iter().map(|(_, w)| w).sum();
+    if total_weight <= 0. {
+        // Every retained run carries zero weight (e.g. they all scored
+        // identically behavioural) - fall back to an unweighted quantile
+        // over the sorted traces rather than collapsing to a single value.
+        let idx = (quantile * (values.len() - 1) as f64).round() as usize;
+        return values.get(idx).map(|(value, _)| *value).unwrap_or(0.);
+    }
+
+    let target = quantile * total_weight;
+
+    let mut cumulative = 0.;
+    for (value, weight) in values.iter() {
+        cumulative += weight;
+        if cumulative >= target {
+            return *value;
+        }
+    }
+
+    values.last().map(|(value, _)| *value).unwrap_or(0.)
+}
+
+/*
+Run `n` Monte Carlo simulations of `model` over `precip`/`pet`, sampling
+each GR4J/GR2M parameter independently from `ranges`, score each run
+against `observed` with `likelihood`, discard runs scoring below
+`threshold`, and summarise the retained, likelihood-weighted runs into
+5/50/95% prediction bands.
+
+:param model: Which conceptual model (GR4J or GR2M) to drive.
+:param n: Number of parameter sets to sample.
+:param ranges: Per-parameter sampling range, keyed by "X1"/"X2" (and
+    "X3"/"X4" for GR4J, which GR2M doesn't use and never looks up).
+:param precip: Catchment average rainfall.
+:param pet: Catchment average potential evapotranspiration.
+:param observed: Observed streamflow used to score each parameter set.
+:param likelihood: Pluggable likelihood/objective function.
+:param threshold: Minimum likelihood score for a parameter set to be
+    considered behavioural.
+
+Likelihood scores such as Nash-Sutcliffe are unbounded below and are
+frequently negative, so they can't be used as GLUE weights directly.
+Retained scores are shifted by the worst behavioural score so every
+weight is non-negative (the worst-scoring retained set gets weight
+zero rather than dropping out of the bands entirely); `behavioural`
+still reports each set's raw, unshifted likelihood.
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn run_ensemble(
+    model: ModelChoice,
+    n: usize,
+    ranges: &HashMap<&str, ParamRange>,
+    precip: &[f64],
+    pet: &[f64],
+    observed: &[f64],
+    likelihood: &dyn Likelihood,
+    threshold: f64,
+) -> EnsembleResult {
+    let mut rng = rand::thread_rng();
+
+    let mut behavioural = Vec::new();
+    let mut traces: Vec<Vec<f64>> = Vec::new();
+
+    for _ in 0..n {
+        let x1 = ranges["X1"].sample(&mut rng);
+        let x2 = ranges["X2"].sample(&mut rng);
+
+        // Each arm samples and scores only the parameters its model uses;
+        // GR2M never looks up "X3"/"X4", so a ranges map without them
+        // works fine for a GR2M ensemble.
+        let (qsim, x3, x4) = match model {
+            ModelChoice::GR4J => {
+                let x3 = ranges["X3"].sample(&mut rng);
+                let x4 = ranges["X4"].sample(&mut rng);
+                let mut gr4j = GR4JModel::create(GR4JParams { x1, x2, x3, x4 });
+                (gr4j.run(precip, pet), Some(x3), Some(x4))
+            }
+            ModelChoice::GR2M => {
+                let mut gr2m = GR2MModel::create(GR2MParams { x1, x2 });
+                (gr2m.run(precip, pet), None, None)
+            }
+        };
+
+        let score = likelihood.score(&qsim, observed);
+        if score < threshold {
+            continue;
+        }
+
+        behavioural.push(match (x3, x4) {
+            (Some(x3), Some(x4)) => BehaviouralParams::GR4J {
+                x1,
+                x2,
+                x3,
+                x4,
+                likelihood: score,
+            },
+            _ => BehaviouralParams::GR2M {
+                x1,
+                x2,
+                likelihood: score,
+            },
+        });
+        traces.push(qsim);
+    }
+
+    let min_likelihood = behavioural
+        .iter()
+        .map(BehaviouralParams::likelihood)
+        .fold(f64::INFINITY, f64::min);
+    let glue_weights: Vec<f64> = behavioural
+        .iter()
+        .map(|b| b.likelihood() - min_likelihood)
+        .collect();
+
+    let n_steps = precip.len();
+    let mut q05 = Vec::with_capacity(n_steps);
+    let mut q50 = Vec::with_capacity(n_steps);
+    let mut q95 = Vec::with_capacity(n_steps);
+
+    for t in 0..n_steps {
+        let mut weighted: Vec<(f64, f64)> = traces
+            .iter()
+            .zip(&glue_weights)
+            .map(|(trace, weight)| (trace[t], *weight))
+            .collect();
+
+        q05.push(weighted_quantile(&mut weighted.clone(), 0.05));
+        q50.push(weighted_quantile(&mut weighted.clone(), 0.50));
+        q95.push(weighted_quantile(&mut weighted, 0.95));
+    }
+
+    EnsembleResult {
+        q05,
+        q50,
+        q95,
+        behavioural,
+    }
+}
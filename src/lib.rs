@@ -1,7 +1,21 @@
+mod batch;
+mod config;
+mod ensemble;
 mod models;
+mod numeric;
+#[cfg(feature = "python")]
+mod python;
+mod reservoir_computing;
 
+pub use batch::{run_batch, BatchModel};
+pub use config::{ConfigError, GR4JConfig};
+
+pub use ensemble::{run_ensemble, BehaviouralParams, EnsembleResult, Likelihood, ModelChoice, NashSutcliffe, ParamRange};
 pub use models::gr2m::{GR2MParams, GR2MModel};
 pub use models::gr4j::{GR4JParams, GR4JModel};
+pub use models::gr2m::{BudgetError as GR2MBudgetError, WaterBudget as GR2MWaterBudget};
+pub use models::gr4j::{BudgetError as GR4JBudgetError, WaterBudget as GR4JWaterBudget};
+pub use reservoir_computing::EchoStateNetwork;
 
 #[cfg(test)]
 mod tests {
@@ -236,4 +250,288 @@ mod tests {
         assert_eq!(gr2m.routing_store, 6.922989036389427);
         assert_eq!(qsim, expected);
     }
+
+    #[test]
+    fn gr4j_closure_test() {
+        let params = crate::GR4JParams {
+            x1: 350.,
+            x2: 0.,
+            x3: 90.,
+            x4: 1.7,
+        };
+        let mut gr4j = crate::GR4JModel::create(params);
+
+        let precip: Vec<f64> = (0..100).map(|i| (i as f64 * 0.3).sin().abs() * 10.).collect();
+        let pet = vec![2.; 100];
+
+        let (_, budgets) = gr4j.run_with_budget(&precip, &pet);
+
+        gr4j::GR4JModel::check_closure(&budgets, 1e-6).expect("budget should close when X2 == 0");
+    }
+
+    #[test]
+    fn gr4j_closure_negative_x2_test() {
+        let params = crate::GR4JParams {
+            x1: 350.,
+            x2: -2.5,
+            x3: 90.,
+            x4: 1.7,
+        };
+        let mut gr4j = crate::GR4JModel::create(params);
+
+        let precip: Vec<f64> = (0..100).map(|i| (i as f64 * 0.3).sin().abs() * 10.).collect();
+        let pet = vec![2.; 100];
+
+        let (_, budgets) = gr4j.run_with_budget(&precip, &pet);
+
+        gr4j::GR4JModel::check_closure(&budgets, 1e-6)
+            .expect("budget should close even when the routing/qd clamp fires");
+    }
+
+    #[test]
+    fn gr2m_closure_test() {
+        let params = crate::GR2MParams { x1: 200., x2: 0.8 };
+        let mut gr2m = crate::GR2MModel::create(params);
+
+        let precip = vec![10., 20., 30., 40., 30., 20., 10.];
+        let pet = vec![5., 5., 5., 5., 5., 5., 5.];
+
+        let (_, budgets) = gr2m.run_with_budget(&precip, &pet);
+
+        crate::GR2MModel::check_closure(&budgets, 1e-6)
+            .expect("budget should close for X2 != 1");
+    }
+
+    #[test]
+    fn gr2m_spinup_converges_test() {
+        let params = crate::GR2MParams { x1: 200., x2: 1.1 };
+        let mut gr2m = crate::GR2MModel::create(params);
+
+        let precip = vec![10., 20., 30., 40., 30., 20., 10.];
+        let pet = vec![5., 5., 5., 5., 5., 5., 5.];
+
+        let (cycles, (production_store, routing_store)) = gr2m.spinup(&precip, &pet, 1e-6, 1000);
+
+        assert!(cycles < 1000, "spinup should converge before max_cycles");
+        assert_eq!(gr2m.production_store, production_store);
+        assert_eq!(gr2m.routing_store, routing_store);
+    }
+
+    #[test]
+    fn ensemble_glue_weights_rescaled_test() {
+        use crate::{run_ensemble, ModelChoice, NashSutcliffe, ParamRange};
+        use std::collections::HashMap;
+
+        let mut ranges = HashMap::new();
+        ranges.insert("X1", ParamRange::Uniform { min: 100., max: 500. });
+        ranges.insert("X2", ParamRange::Uniform { min: -2., max: 2. });
+        ranges.insert("X3", ParamRange::Uniform { min: 20., max: 200. });
+        ranges.insert("X4", ParamRange::Uniform { min: 0.5, max: 5. });
+
+        let precip = vec![10., 20., 30., 20., 10., 5., 15., 25.];
+        let pet = vec![2.; 8];
+        let observed = vec![1., 2., 3., 2., 1., 0.5, 1.5, 2.5];
+
+        // Every sample is scored against its own simulation's mean, which
+        // keeps NashSutcliffe well below zero for effectively all draws,
+        // so a naive unclamped weighting would collapse every quantile.
+        let result = run_ensemble(
+            ModelChoice::GR4J,
+            200,
+            &ranges,
+            &precip,
+            &pet,
+            &observed,
+            &NashSutcliffe,
+            -1e6,
+        );
+
+        assert!(!result.behavioural.is_empty());
+        assert!(
+            result.q05.iter().zip(&result.q95).any(|(lo, hi)| hi > lo),
+            "GLUE bands should not collapse once negative likelihoods are rescaled"
+        );
+    }
+
+    #[test]
+    fn ensemble_empty_behavioural_set_test() {
+        use crate::{run_ensemble, ModelChoice, NashSutcliffe, ParamRange};
+        use std::collections::HashMap;
+
+        let mut ranges = HashMap::new();
+        ranges.insert("X1", ParamRange::Uniform { min: 100., max: 500. });
+        ranges.insert("X2", ParamRange::Uniform { min: -2., max: 2. });
+        ranges.insert("X3", ParamRange::Uniform { min: 20., max: 200. });
+        ranges.insert("X4", ParamRange::Uniform { min: 0.5, max: 5. });
+
+        let precip = vec![10., 20., 30., 20., 10., 5., 15., 25.];
+        let pet = vec![2.; 8];
+        let observed = vec![1., 2., 3., 2., 1., 0.5, 1.5, 2.5];
+
+        // An unreachable threshold means no sample is ever retained; the
+        // quantile bands should fall back to 0 rather than panicking on
+        // an empty trace set.
+        let result = run_ensemble(
+            ModelChoice::GR4J,
+            20,
+            &ranges,
+            &precip,
+            &pet,
+            &observed,
+            &NashSutcliffe,
+            1e6,
+        );
+
+        assert!(result.behavioural.is_empty());
+        assert_eq!(result.q05, vec![0.; precip.len()]);
+        assert_eq!(result.q50, vec![0.; precip.len()]);
+        assert_eq!(result.q95, vec![0.; precip.len()]);
+    }
+
+    #[test]
+    fn ensemble_gr2m_does_not_require_x3_x4_test() {
+        use crate::{run_ensemble, BehaviouralParams, ModelChoice, NashSutcliffe, ParamRange};
+        use std::collections::HashMap;
+
+        let mut ranges = HashMap::new();
+        ranges.insert("X1", ParamRange::Uniform { min: 100., max: 500. });
+        ranges.insert("X2", ParamRange::Uniform { min: 0.5, max: 1.5 });
+
+        let precip = vec![10., 20., 30., 40., 30., 20., 10.];
+        let pet = vec![5.; 7];
+        let observed = vec![1., 2., 3., 4., 3., 2., 1.];
+
+        let result = run_ensemble(
+            ModelChoice::GR2M,
+            50,
+            &ranges,
+            &precip,
+            &pet,
+            &observed,
+            &NashSutcliffe,
+            -1e6,
+        );
+
+        for params in &result.behavioural {
+            assert!(matches!(params, BehaviouralParams::GR2M { .. }));
+        }
+    }
+
+    #[test]
+    fn echo_state_network_fit_correct_round_trip_test() {
+        use crate::{EchoStateNetwork, GR4JModel, GR4JParams};
+
+        let params = GR4JParams {
+            x1: 350.,
+            x2: 0.,
+            x3: 90.,
+            x4: 1.7,
+        };
+        let mut gr4j = GR4JModel::create(params);
+
+        let precip: Vec<f64> = (0..50).map(|i| (i as f64 * 0.2).sin().abs() * 10.).collect();
+        let pet = vec![2.; 50];
+
+        let qsim = gr4j.run(&precip, &pet);
+        let observed: Vec<f64> = qsim.iter().map(|q| q * 1.1 + 0.5).collect();
+
+        let production_store_before = gr4j.production_store;
+        let routing_store_before = gr4j.routing_store;
+
+        let mut esn = EchoStateNetwork::create(20, 0.3, 1e-4, 5);
+        esn.fit(&mut gr4j, &precip, &pet, &observed);
+
+        // `fit` must leave the model's state untouched, since `correct` runs
+        // the same forcing again and expects an identical `qsim`.
+        assert_eq!(gr4j.production_store, production_store_before);
+        assert_eq!(gr4j.routing_store, routing_store_before);
+
+        let corrected = esn.correct(&mut gr4j, &precip, &pet);
+
+        let observed_error: f64 = qsim
+            .iter()
+            .zip(&observed)
+            .map(|(s, o)| (o - s).abs())
+            .sum();
+        let corrected_error: f64 = corrected
+            .iter()
+            .zip(&observed)
+            .map(|(c, o)| (o - c).abs())
+            .sum();
+
+        assert!(
+            corrected_error < observed_error,
+            "fitted correction should reduce error versus the uncorrected simulation"
+        );
+    }
+
+    #[test]
+    fn gr4j_config_round_trip_test() {
+        use crate::GR4JConfig;
+        use std::str::FromStr;
+
+        let toml = "model = \"GR4J\"\n\n\
+            [params]\n\
+            X1 = 350.0\n\
+            X2 = 0.0\n\
+            X3 = 90.0\n\
+            X4 = 1.7\n\n\
+            [state]\n\
+            production_store = 10.0\n\
+            routing_store = 5.0\n";
+
+        let config = GR4JConfig::from_str(toml).expect("valid config should parse");
+
+        assert_eq!(config.params.x1, 350.);
+        assert_eq!(config.params.x2, 0.);
+        assert_eq!(config.params.x3, 90.);
+        assert_eq!(config.params.x4, 1.7);
+        assert_eq!(config.production_store, 10.);
+        assert_eq!(config.routing_store, 5.);
+
+        let round_tripped = GR4JConfig::from_str(&config.to_string())
+            .expect("a config's own Display output should re-parse");
+
+        assert_eq!(round_tripped.params.x1, config.params.x1);
+        assert_eq!(round_tripped.production_store, config.production_store);
+        assert_eq!(round_tripped.routing_store, config.routing_store);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn gr4j_state_checkpoint_round_trip_test() {
+        use crate::{GR4JModel, GR4JParams};
+
+        let params = GR4JParams {
+            x1: 350.,
+            x2: 0.,
+            x3: 90.,
+            x4: 1.7,
+        };
+        let mut gr4j = GR4JModel::create(params);
+
+        let precip: Vec<f64> = (0..20).map(|i| (i as f64 * 0.2).sin().abs() * 10.).collect();
+        let pet = vec![2.; 20];
+        gr4j.run(&precip, &pet);
+
+        let json_path = std::env::temp_dir().join("gr4j_checkpoint_test.json");
+        gr4j.save_state(&json_path).expect("save_state should succeed");
+        let restored = GR4JModel::load_state(&json_path).expect("load_state should succeed");
+        std::fs::remove_file(&json_path).ok();
+
+        assert_eq!(restored.production_store, gr4j.production_store);
+        assert_eq!(restored.routing_store, gr4j.routing_store);
+        assert_eq!(restored.uh1, gr4j.uh1);
+        assert_eq!(restored.uh2, gr4j.uh2);
+
+        let bin_path = std::env::temp_dir().join("gr4j_checkpoint_test.bin");
+        gr4j.save_state_binary(&bin_path)
+            .expect("save_state_binary should succeed");
+        let restored_binary =
+            GR4JModel::load_state_binary(&bin_path).expect("load_state_binary should succeed");
+        std::fs::remove_file(&bin_path).ok();
+
+        assert_eq!(restored_binary.production_store, gr4j.production_store);
+        assert_eq!(restored_binary.routing_store, gr4j.routing_store);
+    }
 }
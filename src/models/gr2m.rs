@@ -1,9 +1,61 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::numeric::aitken_extrapolate;
+
+/*
+Per-timestep water balance accounting for a single `GR2MModel::run`
+step, used to verify mass-closure with `GR2MModel::check_closure`.
+
+`exchange` is surfaced explicitly because X2 is GR2M's non-conservative
+exchange coefficient: `r2 = x2 * r1` scales the routing inflow, so any
+`x2 != 1` imports or exports `(x2 - 1) * r1` of water that doesn't come
+from `precip` or leave as `actual_evap`/`discharge`.
+*/
+#[derive(Clone, Debug)]
+pub struct WaterBudget {
+    pub precip: f64,
+    pub actual_evap: f64,
+    pub exchange: f64,
+    pub delta_production_store: f64,
+    pub delta_routing_store: f64,
+    pub discharge: f64,
+}
+
+/*
+Raised by `GR2MModel::check_closure` when a water budget does not
+balance within the requested tolerance.
+*/
+#[derive(Clone, Debug)]
+pub enum BudgetError {
+    Imbalance { residual: f64, tolerance: f64 },
+}
+
+impl std::fmt::Display for BudgetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BudgetError::Imbalance {
+                residual,
+                tolerance,
+            } => write!(
+                f,
+                "water budget residual {} exceeds tolerance {}",
+                residual, tolerance
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BudgetError {}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(C)]
 pub struct GR2MParams {
     pub x1: f64,
     pub x2: f64,
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(C)]
 pub struct GR2MModel {
     pub params: GR2MParams,
@@ -14,7 +66,7 @@ pub struct GR2MModel {
 impl GR2MModel {
     pub fn create(params: GR2MParams) -> GR2MModel {
         GR2MModel {
-            params: params,
+            params,
 
             // Completely dry initial catchment
             production_store: 0.,
@@ -40,35 +92,224 @@ impl GR2MModel {
     pub fn run(&mut self, precip: &[f64], potential_evap: &[f64]) -> Vec<f64> {
         let mut qsim: Vec<f64> = Vec::with_capacity(precip.len());
 
+        for (p, e) in precip.iter().zip(potential_evap) {
+            let (q, _) = self.step(*p, *e);
+            qsim.push(q);
+        }
+        qsim
+    }
+
+    /*
+    Like `run`, but also returns a per-timestep `WaterBudget` tracking
+    every flux and storage term, so callers can verify mass-balance
+    closure with `check_closure`.
+
+    :param precip: Catchment average rainfall.
+    :param potential_evap: Catchment average potential evapotranspiration.
+    */
+    pub fn run_with_budget(
+        &mut self,
+        precip: &[f64],
+        potential_evap: &[f64],
+    ) -> (Vec<f64>, Vec<WaterBudget>) {
+        let mut qsim: Vec<f64> = Vec::with_capacity(precip.len());
+        let mut budgets: Vec<WaterBudget> = Vec::with_capacity(precip.len());
+
+        for (p, e) in precip.iter().zip(potential_evap) {
+            let (q, budget) = self.step(*p, *e);
+            qsim.push(q);
+            budgets.push(budget);
+        }
+
+        (qsim, budgets)
+    }
+
+    fn step(&mut self, p: f64, e: f64) -> (f64, WaterBudget) {
+        let production_store_before = self.production_store;
+        let routing_store_before = self.routing_store;
+
         let x1 = self.params.x1;
         let x2 = self.params.x2;
 
-        for (p, e) in precip.iter().zip(potential_evap) {
-            let phi = (p / x1).tanh();
-            let psi = (e / x1).tanh();
+        let phi = (p / x1).tanh();
+        let psi = (e / x1).tanh();
 
-            let s1 =
-                (self.production_store + x1 * phi) / (1. + phi * (self.production_store / x1));
+        let s1 =
+            (self.production_store + x1 * phi) / (1. + phi * (self.production_store / x1));
 
-            let p1 = p + self.production_store - s1;
+        let p1 = p + self.production_store - s1;
 
-            let s2 = s1 * (1. - psi) / (1. + psi * (1. - s1 / x1));
+        let s2 = s1 * (1. - psi) / (1. + psi * (1. - s1 / x1));
 
-            self.production_store = s2 / (1. + (s2 / x1).powf(3.)).powf(1. / 3.);
+        self.production_store = s2 / (1. + (s2 / x1).powf(3.)).powf(1. / 3.);
 
-            let p2 = s2 - self.production_store;
+        let actual_evap = s1 - s2;
 
-            let p3 = p1 + p2;
+        let p2 = s2 - self.production_store;
 
-            let r1 = self.routing_store + p3;
+        let p3 = p1 + p2;
 
-            let r2 = x2 * r1;
+        let r1 = self.routing_store + p3;
 
-            let q = r2.powf(2.) / (r2 + 60.);
-            qsim.push(q);
+        let r2 = x2 * r1;
+        let exchange = (x2 - 1.) * r1;
+
+        let q = r2.powf(2.) / (r2 + 60.);
+
+        self.routing_store = r2 - q;
+
+        let budget = WaterBudget {
+            precip: p,
+            actual_evap,
+            exchange,
+            delta_production_store: self.production_store - production_store_before,
+            delta_routing_store: self.routing_store - routing_store_before,
+            discharge: q,
+        };
+
+        (q, budget)
+    }
+
+    /*
+    Check that the water budget returned by `run_with_budget` closes to
+    within `tol`: precipitation in, minus actual evaporation and
+    discharge out, minus the change in storage, should equal the
+    negative of the net exchange introduced by X2 (GR2M's only
+    non-conservative term; it only vanishes when X2 == 1), since
+    `exchange = r2 - r1` is water added to the routing store that never
+    passed through `precip`.
+    */
+    pub fn check_closure(budgets: &[WaterBudget], tol: f64) -> Result<(), BudgetError> {
+        let total_precip: f64 = budgets.iter().map(|b| b.precip).sum();
+        let total_evap: f64 = budgets.iter().map(|b| b.actual_evap).sum();
+        let total_discharge: f64 = budgets.iter().map(|b| b.discharge).sum();
+        let total_exchange: f64 = budgets.iter().map(|b| b.exchange).sum();
+        let total_delta_storage: f64 = budgets
+            .iter()
+            .map(|b| b.delta_production_store + b.delta_routing_store)
+            .sum();
+
+        let residual =
+            total_precip - total_evap - total_discharge - total_delta_storage + total_exchange;
 
-            self.routing_store = r2 - q;
+        if residual.abs() <= tol {
+            Ok(())
+        } else {
+            Err(BudgetError::Imbalance {
+                residual,
+                tolerance: tol,
+            })
         }
-        qsim
+    }
+
+    /*
+    Repeatedly run the model over one climatological cycle of
+    `precip`/`potential_evap`, carrying the end-of-cycle
+    `production_store`/`routing_store` into the next cycle, until both
+    storages reach a fixed point (within `tol`) or `max_cycles` is
+    reached.
+
+    Convergence is accelerated with Aitken's delta-squared method,
+    independently per storage, falling back to plain iteration when
+    the extrapolation's denominator is near zero. Convergence itself
+    is judged on the raw end-of-cycle iterates (before any
+    extrapolation is applied), since the extrapolated value is a
+    projection of where the sequence is heading, not a measurement of
+    how much it has already moved. Returns the number of cycles run
+    and the final equilibrated `(production_store, routing_store)`.
+    */
+    pub fn spinup(
+        &mut self,
+        precip: &[f64],
+        potential_evap: &[f64],
+        tol: f64,
+        max_cycles: usize,
+    ) -> (usize, (f64, f64)) {
+        let mut production_history = vec![self.production_store];
+        let mut routing_history = vec![self.routing_store];
+
+        for cycle in 1..=max_cycles {
+            self.run(precip, potential_evap);
+
+            production_history.push(self.production_store);
+            routing_history.push(self.routing_store);
+
+            let n = production_history.len();
+            let production_delta = (production_history[n - 1] - production_history[n - 2]).abs();
+            let routing_delta = (routing_history[n - 1] - routing_history[n - 2]).abs();
+
+            if production_delta <= tol && routing_delta <= tol {
+                return (cycle, (self.production_store, self.routing_store));
+            }
+
+            if n >= 3 {
+                self.production_store = aitken_extrapolate(
+                    production_history[n - 3],
+                    production_history[n - 2],
+                    production_history[n - 1],
+                );
+                self.routing_store = aitken_extrapolate(
+                    routing_history[n - 3],
+                    routing_history[n - 2],
+                    routing_history[n - 1],
+                );
+            }
+        }
+
+        (max_cycles, (self.production_store, self.routing_store))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl GR2MModel {
+    /*
+    Persist the model's parameters and storages to `path` as JSON, so
+    a run can be resumed later or handed off to another process.
+    */
+    pub fn save_state<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+
+    /*
+    Load a model's parameters and storages previously written by
+    `save_state`.
+    */
+    pub fn load_state<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<GR2MModel, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path)?;
+        let model = serde_json::from_reader(file)?;
+        Ok(model)
+    }
+
+    /*
+    Persist the model's parameters and storages to `path` in a compact
+    binary format, for warm restarts where JSON's size or parse cost
+    matters.
+    */
+    pub fn save_state_binary<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let bytes = bincode::serialize(self)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /*
+    Load a model's parameters and storages previously written by
+    `save_state_binary`.
+    */
+    pub fn load_state_binary<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<GR2MModel, Box<dyn std::error::Error>> {
+        let bytes = std::fs::read(path)?;
+        let model = bincode::deserialize(&bytes)?;
+        Ok(model)
     }
 }
\ No newline at end of file
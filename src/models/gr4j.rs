@@ -3,6 +3,11 @@ use std::collections::HashMap;
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::numeric::aitken_extrapolate;
+
 
 /*
 Unit hydrograph ordinates for UH1 derived from S-curves.
@@ -29,7 +34,64 @@ pub(crate) fn s_curves2(t: f64, x4: f64) -> f64 {
     }
 }
 
+/*
+Per-timestep water balance accounting for a single `GR4JModel::step`
+call, used to verify mass-closure with `GR4JModel::check_closure`.
+
+`groundwater_exchange` is surfaced explicitly because it is GR4J's only
+non-conservative flux: it imports or exports water that doesn't come
+from `precip` or leave as `actual_evap`/`discharge`. It is the *sum* of
+what was actually admitted into `routing_store` and directly into `qd`
+this step, not the nominal `x2 * (routing_store/x3)^3.5` computed once
+and applied to both legs — tracking the post-clamp admitted amount
+(rather than twice the nominal value) is what lets closure hold exactly
+even when a clamp fires (e.g. negative `x2`).
+
+`delta_uh_store` tracks the change in water held in the `uh1`/`uh2`
+convolution buffers (weighted `0.9`/`0.1`, matching how they are drawn
+down into `routing_store` and `qd`); omitting it understates storage
+change for any run that doesn't start and end with empty buffers.
+*/
+#[derive(Clone, Debug)]
+pub struct WaterBudget {
+    pub precip: f64,
+    pub actual_evap: f64,
+    pub percolation: f64,
+    pub groundwater_exchange: f64,
+    pub delta_production_store: f64,
+    pub delta_routing_store: f64,
+    pub delta_uh_store: f64,
+    pub discharge: f64,
+}
+
+/*
+Raised by `GR4JModel::check_closure` when a water budget does not
+balance within the requested tolerance.
+*/
+#[derive(Clone, Debug)]
+pub enum BudgetError {
+    Imbalance { residual: f64, tolerance: f64 },
+}
+
+impl std::fmt::Display for BudgetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BudgetError::Imbalance {
+                residual,
+                tolerance,
+            } => write!(
+                f,
+                "water budget residual {} exceeds tolerance {}",
+                residual, tolerance
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BudgetError {}
+
 #[cfg_attr(feature = "python", pyclass)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone)]
 #[repr(C)]
 pub struct GR4JParams {
@@ -39,7 +101,31 @@ pub struct GR4JParams {
     pub x4: f64,
 }
 
+impl GR4JParams {
+    /*
+    Build a validated set of GR4J parameters. `X1` (production store
+    capacity), `X3` (routing store capacity) and `X4` (unit hydrograph
+    time base) must be positive for the model to be physically
+    meaningful; `X2` (groundwater exchange coefficient) may be any
+    sign.
+    */
+    pub fn new(x1: f64, x2: f64, x3: f64, x4: f64) -> Result<GR4JParams, String> {
+        if x1 <= 0. {
+            return Err(format!("X1 must be positive, got {}", x1));
+        }
+        if x3 <= 0. {
+            return Err(format!("X3 must be positive, got {}", x3));
+        }
+        if x4 <= 0. {
+            return Err(format!("X4 must be positive, got {}", x4));
+        }
+
+        Ok(GR4JParams { x1, x2, x3, x4 })
+    }
+}
+
 #[cfg_attr(feature = "python", pyclass)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(C)]
 pub struct GR4JModel {
     pub params: GR4JParams,
@@ -84,28 +170,18 @@ impl GR4JModel {
         let uh2 = vec![0.; n_uh2];
 
         GR4JModel {
-            params: params,
+            params,
 
             // Completely dry initial catchment
             production_store: 0.,
             routing_store: 0.,
 
-            uh1: uh1,
-            uh2: uh2,
+            uh1,
+            uh2,
         }
     }
 
-    /*
-        Generate simulated streamflow for given rainfall and potential evaporation.
-
-        The resulting simulation is appended to the vector stored in the qsim field.
-
-        :param precip: Catchment average rainfall.
-        :param potential_evap: Catchment average potential evapotranspiration.
-    */
-    pub fn run(&mut self, precip: &[f64], potential_evap: &[f64]) -> Vec<f64> {
-        let mut qsim: Vec<f64> = Vec::with_capacity(precip.len());
-
+    fn unit_hydrograph_ordinates(&self) -> (Vec<f64>, Vec<f64>) {
         let n_uh1 = self.params.x4.ceil() as i32;
         let n_uh2 = (2.0 * self.params.x4).ceil() as i32;
 
@@ -122,77 +198,210 @@ impl GR4JModel {
                 - s_curves2(f64::from(t) - 1., self.params.x4);
         }
 
-        for (p, e) in precip.iter().zip(potential_evap) {
-            let net_evap;
-            let mut routing_pattern;
-            let reservoir_production;
-            if p > e {
-                net_evap = 0.;
-                let scaled_net_precip = (13.0f64).min((p - e) / self.params.x1);
-                let tanh_scaled_net_precip = scaled_net_precip.tanh();
-                reservoir_production = (self.params.x1
-                    * (1. - (self.production_store / self.params.x1).powf(2.))
-                    * tanh_scaled_net_precip)
-                    / (1. + self.production_store / self.params.x1 * tanh_scaled_net_precip);
-
-                routing_pattern = p - e - reservoir_production;
-            } else {
-                let scaled_net_evap = (13.0f64).min((e - p) / self.params.x1);
-                let tanh_scaled_net_evap = scaled_net_evap.tanh();
-
-                let ps_div_x1 =
-                    (2. - self.production_store / self.params.x1) * tanh_scaled_net_evap;
-                net_evap = self.production_store * (ps_div_x1)
-                    / (1.
-                        + (1. - self.production_store / self.params.x1) * tanh_scaled_net_evap);
-
-                reservoir_production = 0.;
-                routing_pattern = 0.;
-            }
+        (uh1_ordinates, uh2_ordinates)
+    }
 
-            self.production_store = self.production_store - net_evap + reservoir_production;
+    /*
+    Advance the model by one timestep and return the simulated discharge
+    together with the water budget fluxes and storage changes that
+    produced it.
+    */
+    fn step(&mut self, p: f64, e: f64, uh1_ordinates: &[f64], uh2_ordinates: &[f64]) -> (f64, WaterBudget) {
+        let production_store_before = self.production_store;
+        let routing_store_before = self.routing_store;
+        // Excludes uh1[0]/uh2[0]: those slots hold the previous step's front
+        // ordinate, already drawn down into routing_store/qd last step, so
+        // they aren't real carried-over storage (see `uh_store_after` below).
+        let uh_store_before =
+            0.9 * self.uh1[1..].iter().sum::<f64>() + 0.1 * self.uh2[1..].iter().sum::<f64>();
+
+        let net_evap;
+        let direct_evap;
+        let mut routing_pattern;
+        let reservoir_production;
+        if p > e {
+            net_evap = 0.;
+            direct_evap = e;
+            let scaled_net_precip = (13.0f64).min((p - e) / self.params.x1);
+            let tanh_scaled_net_precip = scaled_net_precip.tanh();
+            reservoir_production = (self.params.x1
+                * (1. - (self.production_store / self.params.x1).powf(2.))
+                * tanh_scaled_net_precip)
+                / (1. + self.production_store / self.params.x1 * tanh_scaled_net_precip);
+
+            routing_pattern = p - e - reservoir_production;
+        } else {
+            direct_evap = p;
+            let scaled_net_evap = (13.0f64).min((e - p) / self.params.x1);
+            let tanh_scaled_net_evap = scaled_net_evap.tanh();
+
+            let ps_div_x1 =
+                (2. - self.production_store / self.params.x1) * tanh_scaled_net_evap;
+            net_evap = self.production_store * (ps_div_x1)
+                / (1.
+                    + (1. - self.production_store / self.params.x1) * tanh_scaled_net_evap);
+
+            reservoir_production = 0.;
+            routing_pattern = 0.;
+        }
 
-            let percolation = self.production_store
-                / (1. + (self.production_store / 2.25 / self.params.x1).powf(4.)).powf(0.25);
-            routing_pattern += self.production_store - percolation;
+        self.production_store = self.production_store - net_evap + reservoir_production;
 
-            self.production_store = percolation;
+        let percolation = self.production_store
+            / (1. + (self.production_store / 2.25 / self.params.x1).powf(4.)).powf(0.25);
+        routing_pattern += self.production_store - percolation;
 
-            for i in 0..(self.uh1.len() - 1) {
-                self.uh1[i] = self.uh1[i + 1] + uh1_ordinates[i] * routing_pattern;
-            }
-            if let (Some(last_uh1), Some(last_ordinate)) =
-                (self.uh1.last_mut(), uh1_ordinates.last())
-            {
-                *last_uh1 = *last_ordinate * routing_pattern;
-            }
+        self.production_store = percolation;
 
-            for j in 0..(self.uh2.len() - 1) {
-                self.uh2[j] = self.uh2[j + 1] + uh2_ordinates[j] * routing_pattern
-            }
-            if let (Some(last_uh2), Some(last_ordinate)) =
-                (self.uh2.last_mut(), uh2_ordinates.last())
-            {
-                *last_uh2 = *last_ordinate * routing_pattern;
-            }
+        // Shifted in place: uh1[i] reads the *next* slot's old value, so this
+        // can't be expressed as a single iterator pass over one of the slices.
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..(self.uh1.len() - 1) {
+            self.uh1[i] = self.uh1[i + 1] + uh1_ordinates[i] * routing_pattern;
+        }
+        if let (Some(last_uh1), Some(last_ordinate)) =
+            (self.uh1.last_mut(), uh1_ordinates.last())
+        {
+            *last_uh1 = *last_ordinate * routing_pattern;
+        }
 
-            let groundwater_exchange =
-                self.params.x2 * (self.routing_store / self.params.x3).powf(3.5);
-            self.routing_store =
-                (0.0f64).max(self.routing_store + self.uh1[0] * 0.9 + groundwater_exchange);
+        #[allow(clippy::needless_range_loop)]
+        for j in 0..(self.uh2.len() - 1) {
+            self.uh2[j] = self.uh2[j + 1] + uh2_ordinates[j] * routing_pattern
+        }
+        if let (Some(last_uh2), Some(last_ordinate)) =
+            (self.uh2.last_mut(), uh2_ordinates.last())
+        {
+            *last_uh2 = *last_ordinate * routing_pattern;
+        }
 
-            let r2 = self.routing_store
-                / (1. + (self.routing_store / self.params.x3).powf(4.)).powf(0.25);
-            let qr = self.routing_store - r2;
-            self.routing_store = r2;
-            let qd = (0.0f64).max(self.uh2[0] * 0.1 + groundwater_exchange);
-            let q = qr + qd;
+        // Excludes uh1[0]/uh2[0]: those front ordinates are drawn down into
+        // routing_store/qd below, so counting them here as well as in the
+        // routing/discharge deltas would double-count that water.
+        let uh_store_after =
+            0.9 * self.uh1[1..].iter().sum::<f64>() + 0.1 * self.uh2[1..].iter().sum::<f64>();
+
+        let groundwater_exchange =
+            self.params.x2 * (self.routing_store / self.params.x3).powf(3.5);
+
+        let routing_inflow = routing_store_before + self.uh1[0] * 0.9 + groundwater_exchange;
+        self.routing_store = (0.0f64).max(routing_inflow);
+        // Exchange actually admitted into routing_store, which differs from
+        // `groundwater_exchange` when the non-negativity clamp above fires.
+        let exchange_to_routing =
+            self.routing_store - (routing_store_before + self.uh1[0] * 0.9);
+
+        let r2 = self.routing_store
+            / (1. + (self.routing_store / self.params.x3).powf(4.)).powf(0.25);
+        let qr = self.routing_store - r2;
+        self.routing_store = r2;
+
+        let qd = (0.0f64).max(self.uh2[0] * 0.1 + groundwater_exchange);
+        // Same correction as `exchange_to_routing`, for the direct-to-qd leg.
+        let exchange_to_qd = qd - self.uh2[0] * 0.1;
+
+        let q = qr + qd;
+        let admitted_exchange = exchange_to_routing + exchange_to_qd;
+
+        let budget = WaterBudget {
+            precip: p,
+            actual_evap: direct_evap + net_evap,
+            percolation,
+            groundwater_exchange: admitted_exchange,
+            delta_production_store: self.production_store - production_store_before,
+            delta_routing_store: self.routing_store - routing_store_before,
+            delta_uh_store: uh_store_after - uh_store_before,
+            discharge: q,
+        };
+
+        (q, budget)
+    }
+
+    /*
+        Generate simulated streamflow for given rainfall and potential evaporation.
+
+        The resulting simulation is appended to the vector stored in the qsim field.
 
+        :param precip: Catchment average rainfall.
+        :param potential_evap: Catchment average potential evapotranspiration.
+    */
+    pub fn run(&mut self, precip: &[f64], potential_evap: &[f64]) -> Vec<f64> {
+        let mut qsim: Vec<f64> = Vec::with_capacity(precip.len());
+
+        let (uh1_ordinates, uh2_ordinates) = self.unit_hydrograph_ordinates();
+
+        for (p, e) in precip.iter().zip(potential_evap) {
+            let (q, _) = self.step(*p, *e, &uh1_ordinates, &uh2_ordinates);
             qsim.push(q);
         }
         qsim
     }
 
+    /*
+    Like `run`, but also returns a per-timestep `WaterBudget` tracking
+    every flux and storage term, so callers can verify mass-balance
+    closure with `check_closure`.
+
+    :param precip: Catchment average rainfall.
+    :param potential_evap: Catchment average potential evapotranspiration.
+    */
+    pub fn run_with_budget(
+        &mut self,
+        precip: &[f64],
+        potential_evap: &[f64],
+    ) -> (Vec<f64>, Vec<WaterBudget>) {
+        let mut qsim: Vec<f64> = Vec::with_capacity(precip.len());
+        let mut budgets: Vec<WaterBudget> = Vec::with_capacity(precip.len());
+
+        let (uh1_ordinates, uh2_ordinates) = self.unit_hydrograph_ordinates();
+
+        for (p, e) in precip.iter().zip(potential_evap) {
+            let (q, budget) = self.step(*p, *e, &uh1_ordinates, &uh2_ordinates);
+            qsim.push(q);
+            budgets.push(budget);
+        }
+
+        (qsim, budgets)
+    }
+
+    /*
+    Check that the water budget returned by `run_with_budget` closes to
+    within `tol`: precipitation in, minus actual evaporation and
+    discharge out, minus the change in storage (production + routing
+    + the `uh1`/`uh2` convolution buffers), should equal the negative of
+    the net groundwater exchange (water added to `routing_store`/`qd`
+    that never passed through `precip`). `groundwater_exchange` already
+    sums the amount admitted into both legs after their non-negativity
+    clamps, so this holds exactly even when `x2` is negative enough to
+    clamp one of those legs to zero.
+    */
+    pub fn check_closure(budgets: &[WaterBudget], tol: f64) -> Result<(), BudgetError> {
+        let total_precip: f64 = budgets.iter().map(|b| b.precip).sum();
+        let total_evap: f64 = budgets.iter().map(|b| b.actual_evap).sum();
+        let total_discharge: f64 = budgets.iter().map(|b| b.discharge).sum();
+        let total_delta_storage: f64 = budgets
+            .iter()
+            .map(|b| b.delta_production_store + b.delta_routing_store + b.delta_uh_store)
+            .sum();
+        let total_groundwater_exchange: f64 =
+            budgets.iter().map(|b| b.groundwater_exchange).sum();
+
+        let residual = total_precip
+            - total_evap
+            - total_discharge
+            - total_delta_storage
+            + total_groundwater_exchange;
+
+        if residual.abs() <= tol {
+            Ok(())
+        } else {
+            Err(BudgetError::Imbalance {
+                residual,
+                tolerance: tol,
+            })
+        }
+    }
+
     pub fn init(
         &mut self,
         params: &HashMap<&str, f64>,
@@ -222,12 +431,130 @@ impl GR4JModel {
 
         if let Some(unit_hydrographs) = unit_hydrographs {
             if let Some(uh1) = unit_hydrographs.get("uh1") {
-                self.uh1.clone_from(&*uh1);
+                self.uh1.clone_from(uh1);
             }
 
             if let Some(uh2) = unit_hydrographs.get("uh2") {
-                self.uh2.clone_from(&*uh2);
+                self.uh2.clone_from(uh2);
+            }
+        }
+    }
+
+    /*
+    Repeatedly run the model over one climatological cycle of
+    `precip`/`potential_evap`, carrying the end-of-cycle
+    `production_store`/`routing_store` into the next cycle, until both
+    storages reach a fixed point (within `tol`) or `max_cycles` is
+    reached.
+
+    Convergence is accelerated with Aitken's delta-squared method,
+    independently per storage: given three successive end-of-cycle
+    values `s_n, s_{n+1}, s_{n+2}`, the fixed point is extrapolated as
+    `s_n - (s_{n+1}-s_n)^2 / (s_{n+2} - 2*s_{n+1} + s_n)` and used as
+    the initial state for the next cycle. If the denominator is near
+    zero the extrapolation is skipped and the plain iterate is kept.
+
+    Convergence itself is judged on the raw end-of-cycle iterates
+    (before any extrapolation is applied), since the extrapolated
+    value is a projection of where the sequence is heading, not a
+    measurement of how much it has already moved.
+
+    Returns the number of cycles run and the final equilibrated
+    `(production_store, routing_store)`.
+    */
+    pub fn spinup(
+        &mut self,
+        precip: &[f64],
+        potential_evap: &[f64],
+        tol: f64,
+        max_cycles: usize,
+    ) -> (usize, (f64, f64)) {
+        let mut production_history = vec![self.production_store];
+        let mut routing_history = vec![self.routing_store];
+
+        for cycle in 1..=max_cycles {
+            self.run(precip, potential_evap);
+
+            production_history.push(self.production_store);
+            routing_history.push(self.routing_store);
+
+            let n = production_history.len();
+            let production_delta = (production_history[n - 1] - production_history[n - 2]).abs();
+            let routing_delta = (routing_history[n - 1] - routing_history[n - 2]).abs();
+
+            if production_delta <= tol && routing_delta <= tol {
+                return (cycle, (self.production_store, self.routing_store));
+            }
+
+            if n >= 3 {
+                self.production_store = aitken_extrapolate(
+                    production_history[n - 3],
+                    production_history[n - 2],
+                    production_history[n - 1],
+                );
+                self.routing_store = aitken_extrapolate(
+                    routing_history[n - 3],
+                    routing_history[n - 2],
+                    routing_history[n - 1],
+                );
             }
         }
+
+        (max_cycles, (self.production_store, self.routing_store))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl GR4JModel {
+    /*
+    Persist the model's parameters and internal state (storages and
+    unit-hydrograph buffers) to `path` as JSON, so a run can be resumed
+    later or handed off to another process.
+    */
+    pub fn save_state<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+
+    /*
+    Load a model's parameters and internal state previously written by
+    `save_state`.
+    */
+    pub fn load_state<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<GR4JModel, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path)?;
+        let model = serde_json::from_reader(file)?;
+        Ok(model)
+    }
+
+    /*
+    Persist the model's parameters and internal state to `path` in a
+    compact binary format, for warm restarts where JSON's size or
+    parse cost matters.
+    */
+    pub fn save_state_binary<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let bytes = bincode::serialize(self)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /*
+    Load a model's parameters and internal state previously written by
+    `save_state_binary`.
+    */
+    pub fn load_state_binary<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<GR4JModel, Box<dyn std::error::Error>> {
+        let bytes = std::fs::read(path)?;
+        let model = bincode::deserialize(&bytes)?;
+        Ok(model)
     }
 }
@@ -0,0 +1,2 @@
+pub mod gr2m;
+pub mod gr4j;
@@ -0,0 +1,14 @@
+/*
+Aitken's delta-squared extrapolation of the fixed point of a
+convergent sequence from three successive iterates, falling back to
+the plain iterate when the denominator is too close to zero to trust.
+*/
+pub(crate) fn aitken_extrapolate(s_n: f64, s_n1: f64, s_n2: f64) -> f64 {
+    let denominator = s_n2 - 2. * s_n1 + s_n;
+
+    if denominator.abs() < 1e-12 {
+        s_n2
+    } else {
+        s_n - (s_n1 - s_n).powf(2.) / denominator
+    }
+}
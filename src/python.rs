@@ -1,16 +1,133 @@
-use numpy::{IntoPyArray, PyArray1, PyReadonlyArrayDyn};
+// pyo3's #[pymethods]/#[pyfunction] expansion wraps each item in a
+// hidden function, which trips the non_local_definitions lint on
+// newer rustc; see https://github.com/PyO3/pyo3/issues/3386.
+#![allow(non_local_definitions)]
+
+use std::collections::HashMap;
+
+use ndarray::Array2;
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyReadonlyArray2, PyReadonlyArrayDyn};
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 
+use crate::batch::{run_batch, BatchModel};
+use crate::ensemble::{run_ensemble, BehaviouralParams, ModelChoice, NashSutcliffe, ParamRange};
 use crate::models::gr4j::{GR4JModel, GR4JParams};
 
 #[pymodule]
 pub fn hydromodels(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<GR4JModel>()?;
     m.add_class::<GR4JParams>()?;
+    m.add_function(wrap_pyfunction!(run_ensemble_gr4j, m)?)?;
+    m.add_function(wrap_pyfunction!(run_batch_gr4j, m)?)?;
     Ok(())
 }
 
+/// Run one independent `GR4JModel` per row of `precip`/`pet`, each with
+/// its own `GR4JParams`, concurrently when the `parallel` feature is
+/// enabled, and return the simulated streamflow as a 2D numpy array.
+#[pyfunction]
+fn run_batch_gr4j<'py>(
+    py: Python<'py>,
+    params: Vec<PyRef<GR4JParams>>,
+    precip: PyReadonlyArray2<f64>,
+    pet: PyReadonlyArray2<f64>,
+) -> &'py PyArray2<f64> {
+    let precip = precip.as_array();
+    let pet = pet.as_array();
+
+    let models: Vec<BatchModel> = params
+        .into_iter()
+        .map(|p| BatchModel::GR4J(GR4JModel::create((*p).clone())))
+        .collect();
+
+    let forcings: Vec<(Vec<f64>, Vec<f64>)> = precip
+        .outer_iter()
+        .zip(pet.outer_iter())
+        .map(|(p_row, e_row)| (p_row.to_vec(), e_row.to_vec()))
+        .collect();
+
+    let n_models = models.len();
+    let n_steps = forcings.first().map(|(p, _)| p.len()).unwrap_or(0);
+
+    let qsim = run_batch(models, &forcings);
+
+    let flat: Vec<f64> = qsim.into_iter().flatten().collect();
+    Array2::from_shape_vec((n_models, n_steps), flat)
+        .unwrap()
+        .into_pyarray(py)
+}
+
+// (x1, x2, x3, x4, likelihood) per retained behavioural GR4J parameter set.
+type BehaviouralRows = Vec<(f64, f64, f64, f64, f64)>;
+
+/// Run a GR4J GLUE ensemble, sampling each parameter uniformly from
+/// `ranges` (a dict of "X1".."X4" to (min, max) tuples), and return the
+/// 5/50/95% streamflow prediction bands as numpy arrays alongside the
+/// behavioural parameter sets as a list of (x1, x2, x3, x4, likelihood)
+/// tuples.
+#[pyfunction]
+fn run_ensemble_gr4j<'py>(
+    py: Python<'py>,
+    n: usize,
+    ranges: HashMap<&str, (f64, f64)>,
+    precip: PyReadonlyArrayDyn<f64>,
+    pet: PyReadonlyArrayDyn<f64>,
+    observed: PyReadonlyArrayDyn<f64>,
+    threshold: f64,
+) -> (
+    &'py PyArray1<f64>,
+    &'py PyArray1<f64>,
+    &'py PyArray1<f64>,
+    BehaviouralRows,
+) {
+    let uniform_ranges: HashMap<&str, ParamRange> = ranges
+        .into_iter()
+        .map(|(name, (min, max))| (name, ParamRange::Uniform { min, max }))
+        .collect();
+
+    let precip = precip.as_slice().unwrap();
+    let pet = pet.as_slice().unwrap();
+    let observed = observed.as_slice().unwrap();
+
+    let result = run_ensemble(
+        ModelChoice::GR4J,
+        n,
+        &uniform_ranges,
+        precip,
+        pet,
+        observed,
+        &NashSutcliffe,
+        threshold,
+    );
+
+    // This pyfunction always samples `ModelChoice::GR4J`, so `behavioural`
+    // only ever contains `BehaviouralParams::GR4J` entries.
+    let behavioural: BehaviouralRows = result
+        .behavioural
+        .into_iter()
+        .map(|b| match b {
+            BehaviouralParams::GR4J {
+                x1,
+                x2,
+                x3,
+                x4,
+                likelihood,
+            } => (x1, x2, x3, x4, likelihood),
+            BehaviouralParams::GR2M { .. } => {
+                unreachable!("run_ensemble_gr4j only samples ModelChoice::GR4J")
+            }
+        })
+        .collect();
+
+    (
+        result.q05.into_pyarray(py),
+        result.q50.into_pyarray(py),
+        result.q95.into_pyarray(py),
+        behavioural,
+    )
+}
+
 #[pymethods]
 impl GR4JParams {
     #[new]
@@ -38,6 +155,6 @@ impl GR4JModel {
         let precip = precip.as_slice().unwrap();
         let pet = pet.as_slice().unwrap();
 
-        self.run(&precip, &pet).into_pyarray(py)
+        self.run(precip, pet).into_pyarray(py)
     }
 }
@@ -0,0 +1,299 @@
+use rand::Rng;
+
+use crate::models::gr4j::GR4JModel;
+
+type Matrix = Vec<Vec<f64>>;
+
+fn mat_mul(a: &Matrix, b: &Matrix) -> Matrix {
+    let rows = a.len();
+    let inner = b.len();
+    let cols = b[0].len();
+
+    let mut out = vec![vec![0.; cols]; rows];
+    for i in 0..rows {
+        for k in 0..inner {
+            if a[i][k] == 0. {
+                continue;
+            }
+            for j in 0..cols {
+                out[i][j] += a[i][k] * b[k][j];
+            }
+        }
+    }
+    out
+}
+
+fn mat_transpose(a: &Matrix) -> Matrix {
+    let rows = a.len();
+    let cols = a[0].len();
+
+    let mut out = vec![vec![0.; rows]; cols];
+    for i in 0..rows {
+        for j in 0..cols {
+            out[j][i] = a[i][j];
+        }
+    }
+    out
+}
+
+/*
+Invert a square matrix via Gauss-Jordan elimination with partial pivoting.
+*/
+fn mat_inverse(a: &Matrix) -> Matrix {
+    let n = a.len();
+    let mut aug: Matrix = a
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut r = row.clone();
+            r.extend((0..n).map(|j| if i == j { 1. } else { 0. }));
+            r
+        })
+        .collect();
+
+    for col in 0..n {
+        let pivot_row = (col..n)
+            .max_by(|&a, &b| aug[a][col].abs().partial_cmp(&aug[b][col].abs()).unwrap())
+            .unwrap();
+        aug.swap(col, pivot_row);
+
+        let pivot = aug[col][col];
+        for value in aug[col].iter_mut() {
+            *value /= pivot;
+        }
+
+        for row in 0..n {
+            if row == col {
+                continue;
+            }
+            let factor = aug[row][col];
+            // Indexes two distinct rows of the same matrix at once, so this
+            // can't be expressed as a single iterator pass.
+            #[allow(clippy::needless_range_loop)]
+            for k in 0..(2 * n) {
+                aug[row][k] -= factor * aug[col][k];
+            }
+        }
+    }
+
+    aug.into_iter().map(|row| row[n..].to_vec()).collect()
+}
+
+/*
+Estimate the spectral radius of a square matrix via power iteration.
+*/
+fn spectral_radius(w: &Matrix, iterations: usize) -> f64 {
+    let n = w.len();
+    let mut v = vec![1. / (n as f64).sqrt(); n];
+
+    let mut eigenvalue = 0.;
+    for _ in 0..iterations {
+        let mut next = vec![0.; n];
+        for i in 0..n {
+            next[i] = (0..n).map(|j| w[i][j] * v[j]).sum();
+        }
+
+        let norm = next.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm == 0. {
+            return 0.;
+        }
+
+        eigenvalue = norm;
+        v = next.into_iter().map(|x| x / norm).collect();
+    }
+
+    eigenvalue
+}
+
+/*
+Run `gr4j` over `precip`/`pet`, leaving its `production_store`,
+`routing_store`, and unit-hydrograph buffers exactly as they were
+before the call. Without this, `fit` and `correct` would each leave
+the model at a different end-of-run state, so a later call would
+simulate a different `qsim` than the one the readout was trained
+against for the same forcing.
+*/
+fn run_from_snapshot(gr4j: &mut GR4JModel, precip: &[f64], pet: &[f64]) -> Vec<f64> {
+    let production_store = gr4j.production_store;
+    let routing_store = gr4j.routing_store;
+    let uh1 = gr4j.uh1.clone();
+    let uh2 = gr4j.uh2.clone();
+
+    let qsim = gr4j.run(precip, pet);
+
+    gr4j.production_store = production_store;
+    gr4j.routing_store = routing_store;
+    gr4j.uh1 = uh1;
+    gr4j.uh2 = uh2;
+
+    qsim
+}
+
+/*
+An echo state network that learns a data-driven correction for the
+systematic error of a conceptual model, following the hybrid
+GCM-plus-reservoir-computing design of Arcomano et al. (SPEEDY-ML).
+
+The reservoir is driven by `[precip, pet, qsim]` at each step; after
+fitting, `correct` adds the learned residual back onto a simulated
+discharge series.
+*/
+pub struct EchoStateNetwork {
+    size: usize,
+    alpha: f64,
+    beta: f64,
+    washout: usize,
+    w_in: Matrix,
+    w: Matrix,
+    w_out: Option<Matrix>,
+}
+
+const N_INPUTS: usize = 3;
+
+impl EchoStateNetwork {
+    /*
+    Create an echo state network with `size` reservoir units, leak
+    rate `alpha`, ridge regression regularisation `beta`, discarding
+    the first `washout` steps of state history before fitting.
+
+    The reservoir matrix `W` is randomly initialised and rescaled so
+    its spectral radius is just under 1, as required for the echo
+    state property.
+    */
+    pub fn create(size: usize, alpha: f64, beta: f64, washout: usize) -> EchoStateNetwork {
+        let mut rng = rand::thread_rng();
+
+        let w_in: Matrix = (0..size)
+            .map(|_| (0..N_INPUTS).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect();
+
+        let mut w: Matrix = (0..size)
+            .map(|_| {
+                (0..size)
+                    .map(|_| {
+                        if rng.gen_range(0.0..1.0) < 0.1 {
+                            rng.gen_range(-1.0..1.0)
+                        } else {
+                            0.
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let rho = spectral_radius(&w, 100);
+        if rho > 0. {
+            let scale = 0.9 / rho;
+            for row in w.iter_mut() {
+                for value in row.iter_mut() {
+                    *value *= scale;
+                }
+            }
+        }
+
+        EchoStateNetwork {
+            size,
+            alpha,
+            beta,
+            washout,
+            w_in,
+            w,
+            w_out: None,
+        }
+    }
+
+    fn states(&self, precip: &[f64], pet: &[f64], qsim: &[f64]) -> Matrix {
+        let mut x = vec![0.; self.size];
+        let mut states = Vec::with_capacity(precip.len());
+
+        for t in 0..precip.len() {
+            let u = [precip[t], pet[t], qsim[t]];
+
+            let activation: Vec<f64> = self
+                .w_in
+                .iter()
+                .zip(&self.w)
+                .map(|(w_in_row, w_row)| {
+                    let w_in_u: f64 = w_in_row.iter().zip(&u).map(|(w, ui)| w * ui).sum();
+                    let w_x: f64 = w_row.iter().zip(&x).map(|(w, xi)| w * xi).sum();
+                    (w_in_u + w_x).tanh()
+                })
+                .collect();
+
+            x = x
+                .iter()
+                .zip(&activation)
+                .map(|(xi, ai)| (1. - self.alpha) * xi + self.alpha * ai)
+                .collect();
+
+            states.push(x.clone());
+        }
+
+        states
+    }
+
+    /*
+    Fit the readout `W_out` to the residual between `observed` and the
+    GR4J simulation of `precip`/`pet`, using ridge regression over the
+    reservoir states collected after the washout period.
+    */
+    pub fn fit(&mut self, gr4j: &mut GR4JModel, precip: &[f64], pet: &[f64], observed: &[f64]) {
+        let qsim = run_from_snapshot(gr4j, precip, pet);
+        let states = self.states(precip, pet, &qsim);
+
+        let residual: Vec<f64> = qsim
+            .iter()
+            .zip(observed)
+            .map(|(s, o)| o - s)
+            .collect();
+
+        // Features are [x; u] per the SPEEDY-ML-style augmented readout.
+        let design: Matrix = states[self.washout..]
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let t = i + self.washout;
+                let mut row = x.clone();
+                row.extend([precip[t], pet[t], qsim[t]]);
+                row
+            })
+            .collect();
+
+        let target: Matrix = residual[self.washout..].iter().map(|r| vec![*r]).collect();
+
+        let design_t = mat_transpose(&design);
+        let mut gram = mat_mul(&design_t, &design);
+        for (i, row) in gram.iter_mut().enumerate() {
+            row[i] += self.beta;
+        }
+
+        let w_out = mat_mul(&mat_mul(&mat_inverse(&gram), &design_t), &target);
+        self.w_out = Some(mat_transpose(&w_out));
+    }
+
+    /*
+    Apply the learned residual correction on top of a freshly simulated
+    GR4J discharge series for `precip`/`pet`.
+    */
+    pub fn correct(&self, gr4j: &mut GR4JModel, precip: &[f64], pet: &[f64]) -> Vec<f64> {
+        let qsim = run_from_snapshot(gr4j, precip, pet);
+        let states = self.states(precip, pet, &qsim);
+
+        let w_out = self
+            .w_out
+            .as_ref()
+            .expect("EchoStateNetwork::correct called before fit");
+
+        qsim.iter()
+            .zip(&states)
+            .enumerate()
+            .map(|(t, (q, x))| {
+                let mut features = x.clone();
+                features.extend([precip[t], pet[t], *q]);
+
+                let residual: f64 = w_out[0].iter().zip(&features).map(|(w, f)| w * f).sum();
+                q + residual
+            })
+            .collect()
+    }
+}